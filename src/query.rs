@@ -1,11 +1,22 @@
+use crate::model::Resource;
 use queryst::parse;
 use serde_json::value::Value;
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::fmt;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub struct PageParams {
-    pub offset: i64,
-    pub limit: i64,
+/// A pagination strategy for the `page` query parameter family.
+///
+/// JSON:API servers commonly paginate by `offset`/`limit`, by
+/// `page[number]`/`page[size]`, or by an opaque `page[cursor]`. `Query`
+/// detects which keys are present and builds the matching variant; use
+/// [`Pagination::as_offset_limit`] to convert any of them down to an
+/// offset/limit pair.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pagination {
+    Offset { offset: i64, limit: i64 },
+    Paged { number: i64, size: i64 },
+    Cursor { cursor: String, size: i64 },
 }
 
 /// JSON-API Query parameters
@@ -14,11 +25,268 @@ pub struct Query {
     pub _type: String,
     pub include: Option<Vec<String>>,
     pub fields: Option<BTreeMap<String, Vec<String>>>,
-    pub page: Option<PageParams>,
-    pub sort: Option<Vec<String>>,
+    pub page: Option<Pagination>,
+    pub sort: Option<Vec<SortField>>,
     pub filter: Option<Value>,
 }
 
+/// A single key of the `sort` query parameter, e.g. `-created` or `name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortField {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl SortField {
+    /// Parses one comma-separated segment of a `sort` parameter; a leading
+    /// `-` marks the field as descending.
+    pub fn parse(raw: &str) -> SortField {
+        match raw.strip_prefix('-') {
+            Some(field) => SortField {
+                field: field.to_string(),
+                descending: true,
+            },
+            None => SortField {
+                field: raw.to_string(),
+                descending: false,
+            },
+        }
+    }
+
+    /// Renders this field back to its `sort` parameter form, e.g. `-created`.
+    pub fn to_param(&self) -> String {
+        if self.descending {
+            format!("-{}", self.field)
+        } else {
+            self.field.clone()
+        }
+    }
+}
+
+/// A nested tree of relationship names, built from the dotted paths of an
+/// `include` query parameter (e.g. `author.comments.likes`), so a serializer
+/// can walk each level's relationships depth-first.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct IncludeTree {
+    pub children: BTreeMap<String, IncludeTree>,
+}
+
+impl IncludeTree {
+    fn from_paths(paths: &[String]) -> IncludeTree {
+        let mut root = IncludeTree::default();
+        for path in paths {
+            root.insert_path(path);
+        }
+        root
+    }
+
+    fn insert_path(&mut self, path: &str) {
+        let mut node = self;
+        for segment in path.split('.') {
+            node = node
+                .children
+                .entry(segment.to_string())
+                .or_default();
+        }
+    }
+}
+
+/// A structured filter expression, parsed from the JSON object form of the
+/// `filter` query parameter (e.g. `{"age":{"$gte":18},"name":{"$contains":"foo"}}`).
+///
+/// Built via [`FilterExpr::parse`] and evaluated against a [`Resource`]'s
+/// attributes via [`FilterExpr::matches`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterExpr {
+    Eq(String, Value),
+    In(String, Vec<Value>),
+    Contains(String, String),
+    Gt(String, Value),
+    Lt(String, Value),
+    Gte(String, Value),
+    Lte(String, Value),
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+}
+
+/// An error produced while parsing a `filter` query parameter into a [`FilterExpr`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterParseError {
+    /// An operator key (e.g. `$foo`) that this crate doesn't understand.
+    UnknownOperator { field: String, op: String },
+    /// An operator was given an operand of the wrong shape (e.g. `$in` with a non-array).
+    InvalidOperand { field: String, op: String },
+    /// A filter clause (the top-level filter, or a `$or`/`$not` branch) that isn't
+    /// a JSON object, e.g. a string or number inside a `$or` array.
+    NotAnObject { raw: String },
+}
+
+impl fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterParseError::UnknownOperator { field, op } => {
+                write!(f, "unknown filter operator {:?} for field {:?}", op, field)
+            }
+            FilterParseError::InvalidOperand { field, op } => write!(
+                f,
+                "invalid operand for operator {:?} on field {:?}",
+                op, field
+            ),
+            FilterParseError::NotAnObject { raw } => {
+                write!(f, "expected a filter object, got {:?}", raw)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FilterParseError {}
+
+impl FilterExpr {
+    ///
+    /// Parses the JSON object form of the `filter` query parameter into a
+    /// `FilterExpr`, e.g. `{"age":{"$gte":18},"name":{"$contains":"foo"}}`.
+    ///
+    /// Multiple fields, and multiple operators on the same field, are
+    /// combined with `And`. The reserved keys `$or` (an array of nested
+    /// filter objects) and `$not` (a single nested filter object) build
+    /// `Or`/`Not` nodes instead of a field comparison. Unknown operator
+    /// keys are rejected.
+    ///
+    pub fn parse(value: &Value) -> Result<FilterExpr, FilterParseError> {
+        let obj = match value.as_object() {
+            Some(obj) => obj,
+            None => {
+                return Err(FilterParseError::NotAnObject {
+                    raw: value.to_string(),
+                })
+            }
+        };
+
+        let mut clauses = Vec::with_capacity(obj.len());
+        for (field, spec) in obj.iter() {
+            clauses.push(match field.as_str() {
+                "$or" => {
+                    let invalid_operand = || FilterParseError::InvalidOperand {
+                        field: "$or".to_string(),
+                        op: "$or".to_string(),
+                    };
+                    let branches = spec.as_array().ok_or_else(invalid_operand)?;
+                    let parsed = branches
+                        .iter()
+                        .map(FilterExpr::parse)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    FilterExpr::Or(parsed)
+                }
+                "$not" => FilterExpr::Not(Box::new(FilterExpr::parse(spec)?)),
+                _ => Self::parse_field(field, spec)?,
+            });
+        }
+
+        Ok(FilterExpr::And(clauses))
+    }
+
+    fn parse_field(field: &str, spec: &Value) -> Result<FilterExpr, FilterParseError> {
+        let ops = match spec.as_object() {
+            Some(ops) => ops,
+            // A bare value, e.g. `{"name":"foo"}`, is shorthand for `$eq`.
+            None => return Ok(FilterExpr::Eq(field.to_string(), spec.clone())),
+        };
+
+        let mut clauses = Vec::with_capacity(ops.len());
+        for (op, operand) in ops.iter() {
+            let invalid_operand = || FilterParseError::InvalidOperand {
+                field: field.to_string(),
+                op: op.to_string(),
+            };
+
+            clauses.push(match op.as_str() {
+                "$eq" => FilterExpr::Eq(field.to_string(), operand.clone()),
+                "$in" => FilterExpr::In(
+                    field.to_string(),
+                    operand.as_array().ok_or_else(invalid_operand)?.clone(),
+                ),
+                "$contains" => FilterExpr::Contains(
+                    field.to_string(),
+                    operand
+                        .as_str()
+                        .ok_or_else(invalid_operand)?
+                        .to_string(),
+                ),
+                "$gt" => FilterExpr::Gt(field.to_string(), operand.clone()),
+                "$lt" => FilterExpr::Lt(field.to_string(), operand.clone()),
+                "$gte" => FilterExpr::Gte(field.to_string(), operand.clone()),
+                "$lte" => FilterExpr::Lte(field.to_string(), operand.clone()),
+                other => {
+                    return Err(FilterParseError::UnknownOperator {
+                        field: field.to_string(),
+                        op: other.to_string(),
+                    })
+                }
+            });
+        }
+
+        if clauses.len() == 1 {
+            Ok(clauses.into_iter().next().unwrap())
+        } else {
+            Ok(FilterExpr::And(clauses))
+        }
+    }
+
+    ///
+    /// Evaluates this filter against a resource's attributes.
+    ///
+    /// A missing attribute never matches, except under `Not`, where the
+    /// absence of a match satisfies the negation.
+    ///
+    pub fn matches(&self, resource: &Resource) -> bool {
+        match self {
+            FilterExpr::Eq(field, expected) => {
+                resource.attributes.get(field) == Some(expected)
+            }
+            FilterExpr::In(field, values) => resource
+                .attributes
+                .get(field)
+                .map(|actual| values.contains(actual))
+                .unwrap_or(false),
+            FilterExpr::Contains(field, needle) => resource
+                .attributes
+                .get(field)
+                .and_then(Value::as_str)
+                .map(|haystack| haystack.contains(needle.as_str()))
+                .unwrap_or(false),
+            FilterExpr::Gt(field, expected) => {
+                compare_attribute(resource, field, expected) == Some(Ordering::Greater)
+            }
+            FilterExpr::Lt(field, expected) => {
+                compare_attribute(resource, field, expected) == Some(Ordering::Less)
+            }
+            FilterExpr::Gte(field, expected) => matches!(
+                compare_attribute(resource, field, expected),
+                Some(Ordering::Greater) | Some(Ordering::Equal)
+            ),
+            FilterExpr::Lte(field, expected) => matches!(
+                compare_attribute(resource, field, expected),
+                Some(Ordering::Less) | Some(Ordering::Equal)
+            ),
+            FilterExpr::And(clauses) => clauses.iter().all(|clause| clause.matches(resource)),
+            FilterExpr::Or(clauses) => clauses.iter().any(|clause| clause.matches(resource)),
+            FilterExpr::Not(inner) => !inner.matches(resource),
+        }
+    }
+}
+
+/// Orders two attribute values, returning `None` (rather than panicking) when
+/// they're of incomparable types.
+fn compare_attribute(resource: &Resource, field: &str, expected: &Value) -> Option<Ordering> {
+    let actual = resource.attributes.get(field)?;
+    match (actual, expected) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
 //
 // Helper functions to break down the cyclomatic complexity of parameter parsing
 //
@@ -58,13 +326,13 @@ fn ok_params_fields(o: &Value) -> BTreeMap<String, Vec<String>> {
     fields
 }
 
-fn ok_params_sort(o: &Value) -> Option<Vec<String>> {
+fn ok_params_sort(o: &Value) -> Option<Vec<SortField>> {
     match o.pointer("/sort") {
         None => None,
         Some(sort) => match sort.as_str() {
             None => None,
             Some(sort_str) => {
-                let arr: Vec<String> = sort_str.split(',').map(|s| s.to_string()).collect();
+                let arr: Vec<SortField> = sort_str.split(',').map(SortField::parse).collect();
                 Some(arr)
             }
         },
@@ -87,71 +355,199 @@ fn ok_params_filter(o: &Value) -> Option<Value> {
     }
 }
 
-fn ok_params_page(o: &Value) -> PageParams {
-    PageParams {
-        offset: match o.pointer("/page/offset") {
-            None => {
+fn pointer_str<'a>(o: &'a Value, pointer: &str) -> Option<&'a str> {
+    o.pointer(pointer).and_then(Value::as_str)
+}
+
+/// Which `page[...]` keys are present, shared by the lenient and strict
+/// parsing paths so they can't drift on which form wins.
+enum PageKind {
+    Cursor,
+    Paged,
+    Offset,
+}
+
+fn detect_page_kind(o: &Value) -> PageKind {
+    if o.pointer("/page/cursor").is_some() || o.pointer("/page/after").is_some() {
+        PageKind::Cursor
+    } else if o.pointer("/page/number").is_some() || o.pointer("/page/size").is_some() {
+        PageKind::Paged
+    } else {
+        PageKind::Offset
+    }
+}
+
+fn lenient_page_i64(o: &Value, pointer: &str, label: &str) -> i64 {
+    match o.pointer(pointer) {
+        None => {
+            warn!(
+                "Query::from_params : No {} found in {:?}, setting default 0",
+                label, o
+            );
+            0
+        }
+        Some(num) => {
+            if num.is_string() {
+                match num.as_str().map(str::parse::<i64>) {
+                    Some(y) => y.unwrap_or(0),
+                    None => {
+                        warn!(
+                            "Query::from_params : {} found in {:?}, not able to \
+                                           parse it - setting default 0",
+                            label, o
+                        );
+                        0
+                    }
+                }
+            } else {
                 warn!(
-                    "Query::from_params : No page/offset found in {:?}, setting \
-                                   default 0",
-                    o
+                    "Query::from_params : {} found in {:?}, but it is not an \
+                                   expected type - setting default 0",
+                    label, o
                 );
                 0
             }
-            Some(num) => {
-                if num.is_string() {
-                    match num.as_str().map(str::parse::<i64>) {
-                        Some(y) => y.unwrap_or(0),
-                        None => {
-                            warn!(
-                                "Query::from_params : page/offset found in {:?}, \
-                                               not able not able to parse it - setting default 0",
-                                o
-                            );
-                            0
-                        }
-                    }
-                } else {
-                    warn!(
-                        "Query::from_params : page/offset found in {:?}, but it is \
-                                       not an expected type - setting default 0",
-                        o
-                    );
-                    0
+        }
+    }
+}
+
+fn ok_params_page(o: &Value) -> Pagination {
+    match detect_page_kind(o) {
+        PageKind::Cursor => Pagination::Cursor {
+            cursor: pointer_str(o, "/page/cursor")
+                .or_else(|| pointer_str(o, "/page/after"))
+                .unwrap_or_default()
+                .to_string(),
+            size: lenient_page_i64(o, "/page/size", "page/size"),
+        },
+        PageKind::Paged => Pagination::Paged {
+            number: lenient_page_i64(o, "/page/number", "page/number"),
+            size: lenient_page_i64(o, "/page/size", "page/size"),
+        },
+        PageKind::Offset => Pagination::Offset {
+            offset: lenient_page_i64(o, "/page/offset", "page/offset"),
+            limit: lenient_page_i64(o, "/page/limit", "page/limit"),
+        },
+    }
+}
+
+/// Parameters recognised by [`Query::try_from_params`]; anything else
+/// produces a [`QueryError::UnknownParameter`].
+const KNOWN_PARAMS: &[&str] = &["include", "fields", "sort", "filter", "page"];
+
+/// An error produced while strictly parsing a query parameter string via
+/// [`Query::try_from_params`].
+///
+/// Unlike [`Query::from_params`], which silently substitutes defaults,
+/// `try_from_params` accumulates every problem it finds in one pass so they
+/// can be mapped straight onto JSON:API `errors` objects.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryError {
+    InvalidLimit { raw: String },
+    InvalidOffset { raw: String },
+    InvalidPageNumber { raw: String },
+    InvalidPageSize { raw: String },
+    MalformedFilter { raw: String },
+    InvalidFilterOperator { raw: String, reason: String },
+    UnknownParameter { name: String },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::InvalidLimit { raw } => write!(f, "invalid page[limit]: {:?}", raw),
+            QueryError::InvalidOffset { raw } => write!(f, "invalid page[offset]: {:?}", raw),
+            QueryError::InvalidPageNumber { raw } => write!(f, "invalid page[number]: {:?}", raw),
+            QueryError::InvalidPageSize { raw } => write!(f, "invalid page[size]: {:?}", raw),
+            QueryError::MalformedFilter { raw } => write!(f, "malformed filter: {:?}", raw),
+            QueryError::InvalidFilterOperator { raw, reason } => {
+                write!(f, "invalid filter {:?}: {}", raw, reason)
+            }
+            QueryError::UnknownParameter { name } => write!(f, "unknown parameter: {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn strict_params_unknown(o: &Value, errors: &mut Vec<QueryError>) {
+    if let Some(obj) = o.as_object() {
+        for key in obj.keys() {
+            if !KNOWN_PARAMS.contains(&key.as_str()) {
+                errors.push(QueryError::UnknownParameter { name: key.clone() });
+            }
+        }
+    }
+}
+
+fn strict_params_filter(o: &Value, errors: &mut Vec<QueryError>) -> Option<Value> {
+    match o.pointer("/filter") {
+        None => None,
+        Some(x) => match x.as_str().and_then(|raw| serde_json::from_str::<Value>(raw).ok()) {
+            Some(parsed) if parsed.is_object() => match FilterExpr::parse(&parsed) {
+                Ok(_) => Some(parsed),
+                Err(err) => {
+                    errors.push(QueryError::InvalidFilterOperator {
+                        raw: x.as_str().unwrap_or_default().to_string(),
+                        reason: err.to_string(),
+                    });
+                    None
                 }
+            },
+            _ => {
+                errors.push(QueryError::MalformedFilter {
+                    raw: x.as_str().unwrap_or_default().to_string(),
+                });
+                None
             }
         },
-        limit: match o.pointer("/page/limit") {
+    }
+}
+
+fn strict_page_i64(
+    o: &Value,
+    pointer: &str,
+    errors: &mut Vec<QueryError>,
+    on_error: impl Fn(String) -> QueryError,
+) -> i64 {
+    match o.pointer(pointer) {
+        None => 0,
+        Some(value) => match value.as_str().and_then(|raw| raw.parse::<i64>().ok()) {
+            Some(parsed) => parsed,
             None => {
-                warn!(
-                    "Query::from_params : No page/limit found in {:?}, setting \
-                                   default 0",
-                    o
-                );
+                errors.push(on_error(value.as_str().unwrap_or_default().to_string()));
                 0
             }
-            Some(num) => {
-                if num.is_string() {
-                    match num.as_str().map(str::parse::<i64>) {
-                        Some(y) => y.unwrap_or(0),
-                        None => {
-                            warn!(
-                                "Query::from_params : page/limit found in {:?}, \
-                                               not able not able to parse it - setting default 0",
-                                o
-                            );
-                            0
-                        }
-                    }
-                } else {
-                    warn!(
-                        "Query::from_params : page/limit found in {:?}, but it is \
-                                       not an expected type - setting default 0",
-                        o
-                    );
-                    0
-                }
-            }
+        },
+    }
+}
+
+fn strict_params_page(o: &Value, errors: &mut Vec<QueryError>) -> Pagination {
+    match detect_page_kind(o) {
+        PageKind::Cursor => Pagination::Cursor {
+            cursor: pointer_str(o, "/page/cursor")
+                .or_else(|| pointer_str(o, "/page/after"))
+                .unwrap_or_default()
+                .to_string(),
+            size: strict_page_i64(o, "/page/size", errors, |raw| {
+                QueryError::InvalidPageSize { raw }
+            }),
+        },
+        PageKind::Paged => Pagination::Paged {
+            number: strict_page_i64(o, "/page/number", errors, |raw| {
+                QueryError::InvalidPageNumber { raw }
+            }),
+            size: strict_page_i64(o, "/page/size", errors, |raw| {
+                QueryError::InvalidPageSize { raw }
+            }),
+        },
+        PageKind::Offset => Pagination::Offset {
+            offset: strict_page_i64(o, "/page/offset", errors, |raw| {
+                QueryError::InvalidOffset { raw }
+            }),
+            limit: strict_page_i64(o, "/page/limit", errors, |raw| {
+                QueryError::InvalidLimit { raw }
+            }),
         },
     }
 }
@@ -198,16 +594,52 @@ impl Query {
         }
     }
 
+    ///
+    /// Takes a query parameter string and returns a Query, or every
+    /// [`QueryError`] found while parsing it. Unlike `from_params`, which
+    /// substitutes defaults for bad input, this collects every problem in a
+    /// single pass rather than bailing on the first one, matching the
+    /// JSON:API recommendation to respond with errors for unparseable query
+    /// parameters.
+    ///
+    /// ```
+    /// use jsonapi::query::{Query, QueryError};
+    /// let errors = Query::try_from_params("page[offset]=x").unwrap_err();
+    /// assert_eq!(errors, vec![QueryError::InvalidOffset { raw: "x".into() }]);
+    /// ```
+    ///
+    pub fn try_from_params(params: &str) -> Result<Query, Vec<QueryError>> {
+        let o = parse(params).unwrap_or_else(|_| Value::Object(serde_json::Map::new()));
+
+        let mut errors = Vec::new();
+        strict_params_unknown(&o, &mut errors);
+        let page = strict_params_page(&o, &mut errors);
+        let filter = strict_params_filter(&o, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Query {
+            _type: "none".into(),
+            include: ok_params_include(&o),
+            fields: Some(ok_params_fields(&o)),
+            page: Some(page),
+            sort: ok_params_sort(&o),
+            filter,
+        })
+    }
+
     ///
     /// Builds a query parameter string from a Query
     ///
     /// ```
-    /// use jsonapi::query::{Query, PageParams};
+    /// use jsonapi::query::{Pagination, Query};
     /// let query = Query {
     ///   _type: "post".into(),
     ///   include: Some(vec!["author".into()]),
     ///   fields: None,
-    ///   page: Some(PageParams {
+    ///   page: Some(Pagination::Offset {
     ///     limit: 5,
     ///     offset: 10,
     ///   }),
@@ -237,7 +669,12 @@ impl Query {
         }
 
         if let Some(ref sort) = self.sort {
-            params.push(format!("sort={}", sort.join(",")))
+            let joined = sort
+                .iter()
+                .map(SortField::to_param)
+                .collect::<Vec<_>>()
+                .join(",");
+            params.push(format!("sort={}", joined))
         }
 
         if let Some(ref filter) = self.filter {
@@ -250,10 +687,147 @@ impl Query {
 
         params.join("&")
     }
+
+    ///
+    /// Applies this query's `filter`, if any, to an in-memory collection of
+    /// resources. A filter that fails to parse (e.g. an unknown operator)
+    /// is returned as an error rather than silently passing every resource
+    /// through unfiltered — callers that use `filter` for scoping or
+    /// authorization must not fail open on a malformed filter.
+    ///
+    pub fn apply_filter(&self, docs: Vec<Resource>) -> Result<Vec<Resource>, FilterParseError> {
+        let filter = match &self.filter {
+            None => return Ok(docs),
+            Some(filter) => filter,
+        };
+
+        let expr = FilterExpr::parse(filter)?;
+        Ok(docs.into_iter().filter(|doc| expr.matches(doc)).collect())
+    }
+
+    ///
+    /// Applies this query's `sort`, if any, to an in-memory collection of
+    /// resources as a stable multi-key sort. A field absent on a given
+    /// resource leaves that resource's relative order unchanged for that
+    /// key, falling through to the next one.
+    ///
+    pub fn apply_sort(&self, docs: &mut [Resource]) {
+        let fields = match &self.sort {
+            None => return,
+            Some(fields) => fields,
+        };
+
+        docs.sort_by(|a, b| {
+            for sort_field in fields {
+                let ordering =
+                    compare_sort_values(a, b, &sort_field.field, sort_field.descending);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+    }
+
+    ///
+    /// Parses this query's `include` paths (e.g. `author.comments.likes`)
+    /// into an [`IncludeTree`] so a serializer can walk nested relationships
+    /// depth-first instead of re-splitting each path itself.
+    ///
+    pub fn include_tree(&self) -> IncludeTree {
+        match &self.include {
+            None => IncludeTree::default(),
+            Some(paths) => IncludeTree::from_paths(paths),
+        }
+    }
+
+    ///
+    /// Applies this query's sparse fieldset to a single resource, dropping
+    /// any attribute not listed under `fields[<resource._type>]`. A
+    /// resource whose `_type` has no entry in `fields` is left untouched.
+    ///
+    pub fn apply_fields(&self, resource: &mut Resource) {
+        let allowed = match self.fields.as_ref().and_then(|f| f.get(&resource._type)) {
+            None => return,
+            Some(allowed) => allowed,
+        };
+
+        resource.attributes.retain(|key, _| allowed.contains(key));
+    }
 }
 
-impl PageParams {
+/// Orders two resources by a single sort key. Numbers compare numerically,
+/// strings lexically, and explicit `null`s sort last regardless of sort
+/// direction; a resource missing the key compares as `Equal` so it doesn't
+/// get reordered on that key.
+fn compare_sort_values(a: &Resource, b: &Resource, field: &str, descending: bool) -> Ordering {
+    match (a.attributes.get(field), b.attributes.get(field)) {
+        (Some(a_value), Some(b_value)) => compare_sort_value_pair(a_value, b_value, descending),
+        _ => Ordering::Equal,
+    }
+}
+
+/// Compares two non-missing attribute values. `null` placement is decided
+/// before `descending` is applied, so nulls sort last for both ascending and
+/// descending fields instead of flipping to first on a descending field.
+fn compare_sort_value_pair(a: &Value, b: &Value, descending: bool) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ => {
+            let ordering = match (a, b) {
+                (Value::Number(a), Value::Number(b)) => a
+                    .as_f64()
+                    .unwrap_or(0.0)
+                    .partial_cmp(&b.as_f64().unwrap_or(0.0))
+                    .unwrap_or(Ordering::Equal),
+                (Value::String(a), Value::String(b)) => a.cmp(b),
+                _ => Ordering::Equal,
+            };
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        }
+    }
+}
+
+impl Pagination {
+    ///
+    /// Round-trips this pagination strategy back into its query-parameter
+    /// form: `offset`/`limit`, `number`/`size`, or `cursor`/`size`. A cursor
+    /// read from `page[after]` is written back as `page[cursor]`.
+    ///
     pub fn to_params(&self) -> String {
-        format!("page[limit]={}&page[offset]={}", self.limit, self.offset)
+        match self {
+            Pagination::Offset { offset, limit } => {
+                format!("page[limit]={}&page[offset]={}", limit, offset)
+            }
+            Pagination::Paged { number, size } => {
+                format!("page[number]={}&page[size]={}", number, size)
+            }
+            Pagination::Cursor { cursor, size } => {
+                format!("page[cursor]={}&page[size]={}", cursor, size)
+            }
+        }
+    }
+
+    ///
+    /// Converts any pagination strategy down to an `(offset, limit)` pair so
+    /// existing offset-based consumers keep working regardless of which
+    /// form the client used. `page[number]` is 1-indexed; a cursor carries
+    /// no absolute position, so it converts to offset `0`.
+    ///
+    pub fn as_offset_limit(&self) -> (i64, i64) {
+        match self {
+            Pagination::Offset { offset, limit } => (*offset, *limit),
+            Pagination::Paged { number, size } => {
+                let zero_indexed_page = if *number > 0 { number.saturating_sub(1) } else { 0 };
+                (zero_indexed_page.saturating_mul(*size), *size)
+            }
+            Pagination::Cursor { size, .. } => (0, *size),
+        }
     }
 }
@@ -1,7 +1,21 @@
 extern crate env_logger;
 extern crate jsonapi;
 
+use jsonapi::model::Resource;
 use jsonapi::query::*;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+fn resource(attributes: HashMap<String, serde_json::Value>) -> Resource {
+    Resource {
+        _type: "people".into(),
+        id: "1".into(),
+        attributes,
+        relationships: None,
+        links: None,
+        meta: None,
+    }
+}
 
 #[test]
 fn can_print() {
@@ -12,12 +26,12 @@ fn can_print() {
     );
     println!("Query is {:?}", query);
 
-    let pageparams = PageParams {
+    let pageparams = Pagination::Offset {
         limit: 1,
         offset: 1,
     };
 
-    println!("PageParams is {:?}", pageparams);
+    println!("Pagination is {:?}", pageparams);
 }
 
 #[test]
@@ -38,10 +52,11 @@ fn can_parse() {
 
     match query.page {
         None => assert!(false),
-        Some(page) => {
-            assert_eq!(page.limit, 1);
-            assert_eq!(page.offset, 3);
+        Some(Pagination::Offset { limit, offset }) => {
+            assert_eq!(limit, 1);
+            assert_eq!(offset, 3);
         }
+        Some(_) => assert!(false),
     }
 
     match query.fields {
@@ -72,7 +87,13 @@ fn can_parse() {
         None => assert!(false),
         Some(sort) => {
             assert_eq!(sort.len(), 1);
-            assert_eq!(sort[0], "name");
+            assert_eq!(
+                sort[0],
+                SortField {
+                    field: "name".into(),
+                    descending: false,
+                }
+            );
         }
     }
 
@@ -96,10 +117,11 @@ fn can_parse_and_provide_defaults_for_partial_fields() {
 
     match query.page {
         None => assert!(false),
-        Some(page) => {
-            assert_eq!(page.limit, 0);
-            assert_eq!(page.offset, 0);
+        Some(Pagination::Offset { limit, offset }) => {
+            assert_eq!(limit, 0);
+            assert_eq!(offset, 0);
         }
+        Some(_) => assert!(false),
     }
 
     match query.sort {
@@ -156,30 +178,33 @@ fn can_parse_and_handle_missing_page_values() {
 
     match query.page {
         None => assert!(false),
-        Some(pageparams) => {
-            assert_eq!(pageparams.offset, 0);
-            assert_eq!(pageparams.limit, 0);
+        Some(Pagination::Offset { offset, limit }) => {
+            assert_eq!(offset, 0);
+            assert_eq!(limit, 0);
         }
+        Some(_) => assert!(false),
     }
 
     let query = Query::from_params("page[offset]=&page[limit]=");
 
     match query.page {
         None => assert!(false),
-        Some(pageparams) => {
-            assert_eq!(pageparams.offset, 0);
-            assert_eq!(pageparams.limit, 0);
+        Some(Pagination::Offset { offset, limit }) => {
+            assert_eq!(offset, 0);
+            assert_eq!(limit, 0);
         }
+        Some(_) => assert!(false),
     }
 
     let query = Query::from_params("page[offset]=/&page[limit]=/");
 
     match query.page {
         None => assert!(false),
-        Some(pageparams) => {
-            assert_eq!(pageparams.offset, 0);
-            assert_eq!(pageparams.limit, 0);
+        Some(Pagination::Offset { offset, limit }) => {
+            assert_eq!(offset, 0);
+            assert_eq!(limit, 0);
         }
+        Some(_) => assert!(false),
     }
 }
 
@@ -200,10 +225,11 @@ fn can_parse_and_use_defaults_for_invalid_values() {
 
     match query.page {
         None => assert!(false),
-        Some(page) => {
-            assert_eq!(page.limit, 0);
-            assert_eq!(page.offset, 0);
+        Some(Pagination::Offset { limit, offset }) => {
+            assert_eq!(limit, 0);
+            assert_eq!(offset, 0);
         }
+        Some(_) => assert!(false),
     }
 
     match query.sort {
@@ -307,7 +333,10 @@ fn can_generate_string_sort() {
         include: None,
         fields: None,
         page: None,
-        sort: Some(vec!["name".into()]),
+        sort: Some(vec![SortField {
+            field: "name".into(),
+            descending: false,
+        }]),
         filter: None,
     };
 
@@ -324,7 +353,16 @@ fn can_generate_string_sort_multiple() {
         include: None,
         fields: None,
         page: None,
-        sort: Some(vec!["-name".into(), "created".into()]),
+        sort: Some(vec![
+            SortField {
+                field: "name".into(),
+                descending: true,
+            },
+            SortField {
+                field: "created".into(),
+                descending: false,
+            },
+        ]),
         filter: None,
     };
 
@@ -437,7 +475,7 @@ fn can_generate_page_fields() {
         _type: "none".into(),
         include: None,
         fields: None,
-        page: Some(PageParams {
+        page: Some(Pagination::Offset {
             limit: 5,
             offset: 10,
         }),
@@ -449,3 +487,596 @@ fn can_generate_page_fields() {
 
     assert_eq!(query_string, "page[limit]=5&page[offset]=10");
 }
+
+#[test]
+fn filter_expr_parses_operators() {
+    let _ = env_logger::try_init();
+
+    let expr = FilterExpr::parse(&json!({"age": {"$gte": 18}, "name": {"$contains": "foo"}}))
+        .expect("parse should succeed");
+
+    assert_eq!(
+        expr,
+        FilterExpr::And(vec![
+            FilterExpr::Gte("age".into(), json!(18)),
+            FilterExpr::Contains("name".into(), "foo".into()),
+        ])
+    );
+}
+
+#[test]
+fn filter_expr_parses_or_and_not() {
+    let _ = env_logger::try_init();
+
+    let expr = FilterExpr::parse(&json!({
+        "$or": [{"age": {"$lt": 18}}, {"name": {"$eq": "admin"}}],
+        "$not": {"active": {"$eq": false}},
+    }))
+    .expect("parse should succeed");
+
+    assert_eq!(
+        expr,
+        FilterExpr::And(vec![
+            FilterExpr::Not(Box::new(FilterExpr::Eq("active".into(), json!(false)))),
+            FilterExpr::Or(vec![
+                FilterExpr::Lt("age".into(), json!(18)),
+                FilterExpr::Eq("name".into(), json!("admin")),
+            ]),
+        ])
+    );
+}
+
+#[test]
+fn filter_expr_parse_rejects_non_array_or_operand() {
+    let _ = env_logger::try_init();
+
+    let result = FilterExpr::parse(&json!({"$or": {"age": {"$lt": 18}}}));
+
+    assert_eq!(
+        result,
+        Err(FilterParseError::InvalidOperand {
+            field: "$or".into(),
+            op: "$or".into(),
+        })
+    );
+}
+
+#[test]
+fn filter_expr_parse_rejects_non_object_or_element_instead_of_failing_open() {
+    let _ = env_logger::try_init();
+
+    let result = FilterExpr::parse(&json!({
+        "$or": ["nonsense", {"owner": {"$eq": "someone"}}],
+    }));
+
+    assert_eq!(
+        result,
+        Err(FilterParseError::NotAnObject {
+            raw: "\"nonsense\"".into(),
+        })
+    );
+}
+
+#[test]
+fn filter_expr_parse_rejects_unknown_operator() {
+    let _ = env_logger::try_init();
+
+    let result = FilterExpr::parse(&json!({"age": {"$nope": 18}}));
+
+    assert_eq!(
+        result,
+        Err(FilterParseError::UnknownOperator {
+            field: "age".into(),
+            op: "$nope".into(),
+        })
+    );
+}
+
+#[test]
+fn filter_expr_parse_rejects_non_array_in_operand() {
+    let _ = env_logger::try_init();
+
+    let result = FilterExpr::parse(&json!({"age": {"$in": "not-an-array"}}));
+
+    assert_eq!(
+        result,
+        Err(FilterParseError::InvalidOperand {
+            field: "age".into(),
+            op: "$in".into(),
+        })
+    );
+}
+
+#[test]
+fn filter_expr_parse_rejects_non_string_contains_operand() {
+    let _ = env_logger::try_init();
+
+    let result = FilterExpr::parse(&json!({"name": {"$contains": 123}}));
+
+    assert_eq!(
+        result,
+        Err(FilterParseError::InvalidOperand {
+            field: "name".into(),
+            op: "$contains".into(),
+        })
+    );
+}
+
+#[test]
+fn filter_expr_matches_eq_and_missing_attribute() {
+    let _ = env_logger::try_init();
+
+    let mut attrs = HashMap::new();
+    attrs.insert("name".to_string(), json!("foo"));
+    let doc = resource(attrs);
+
+    assert!(FilterExpr::Eq("name".into(), json!("foo")).matches(&doc));
+    assert!(!FilterExpr::Eq("missing".into(), json!("foo")).matches(&doc));
+    assert!(FilterExpr::Not(Box::new(FilterExpr::Eq("missing".into(), json!("foo")))).matches(&doc));
+}
+
+#[test]
+fn filter_expr_matches_ordering_ignores_type_mismatch() {
+    let _ = env_logger::try_init();
+
+    let mut attrs = HashMap::new();
+    attrs.insert("age".to_string(), json!("not a number"));
+    let doc = resource(attrs);
+
+    assert!(!FilterExpr::Gte("age".into(), json!(18)).matches(&doc));
+}
+
+#[test]
+fn query_apply_filter_filters_collection() {
+    let _ = env_logger::try_init();
+
+    let mut young = HashMap::new();
+    young.insert("age".to_string(), json!(10));
+    let mut old = HashMap::new();
+    old.insert("age".to_string(), json!(30));
+
+    let query = Query {
+        _type: "people".into(),
+        include: None,
+        fields: None,
+        page: None,
+        sort: None,
+        filter: Some(json!({"age": {"$gte": 18}})),
+    };
+
+    let docs = vec![resource(young), resource(old.clone())];
+    let filtered = query.apply_filter(docs).expect("filter should parse");
+
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].attributes.get("age"), Some(&json!(30)));
+}
+
+#[test]
+fn query_apply_filter_errors_on_unknown_operator_instead_of_failing_open() {
+    let _ = env_logger::try_init();
+
+    let mut attrs = HashMap::new();
+    attrs.insert("owner".to_string(), json!("someone-else"));
+
+    let query = Query {
+        _type: "people".into(),
+        include: None,
+        fields: None,
+        page: None,
+        sort: None,
+        filter: Some(json!({"owner": {"$eqq": "me"}})),
+    };
+
+    let docs = vec![resource(attrs)];
+    let err = query.apply_filter(docs).unwrap_err();
+
+    assert_eq!(
+        err,
+        FilterParseError::UnknownOperator {
+            field: "owner".into(),
+            op: "$eqq".into(),
+        }
+    );
+}
+
+#[test]
+fn try_from_params_ok() {
+    let _ = env_logger::try_init();
+
+    let query = Query::try_from_params("include=author&page[offset]=3&page[limit]=1")
+        .expect("well-formed params should parse");
+
+    assert_eq!(query.include, Some(vec!["author".to_string()]));
+    assert_eq!(
+        query.page,
+        Some(Pagination::Offset {
+            offset: 3,
+            limit: 1,
+        })
+    );
+}
+
+#[test]
+fn try_from_params_collects_invalid_offset_and_limit() {
+    let _ = env_logger::try_init();
+
+    let errors = Query::try_from_params("page[offset]=x&page[limit]=y").unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            QueryError::InvalidOffset { raw: "x".into() },
+            QueryError::InvalidLimit { raw: "y".into() },
+        ]
+    );
+}
+
+#[test]
+fn try_from_params_rejects_unknown_parameter() {
+    let _ = env_logger::try_init();
+
+    let errors = Query::try_from_params("bogus=1").unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![QueryError::UnknownParameter {
+            name: "bogus".into()
+        }]
+    );
+}
+
+#[test]
+fn try_from_params_rejects_malformed_filter() {
+    let _ = env_logger::try_init();
+
+    let errors = Query::try_from_params("filter=not-json").unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![QueryError::MalformedFilter {
+            raw: "not-json".into()
+        }]
+    );
+}
+
+#[test]
+fn try_from_params_rejects_unknown_filter_operator() {
+    let _ = env_logger::try_init();
+
+    let errors = Query::try_from_params(r#"filter={"owner":{"$eqq":"me"}}"#).unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![QueryError::InvalidFilterOperator {
+            raw: r#"{"owner":{"$eqq":"me"}}"#.into(),
+            reason: FilterParseError::UnknownOperator {
+                field: "owner".into(),
+                op: "$eqq".into(),
+            }
+            .to_string(),
+        }]
+    );
+}
+
+#[test]
+fn can_parse_page_number_and_size() {
+    let _ = env_logger::try_init();
+
+    let query = Query::from_params("page[number]=2&page[size]=10");
+
+    assert_eq!(
+        query.page,
+        Some(Pagination::Paged {
+            number: 2,
+            size: 10,
+        })
+    );
+}
+
+#[test]
+fn can_parse_page_cursor() {
+    let _ = env_logger::try_init();
+
+    let query = Query::from_params("page[cursor]=abc123&page[size]=10");
+
+    assert_eq!(
+        query.page,
+        Some(Pagination::Cursor {
+            cursor: "abc123".into(),
+            size: 10,
+        })
+    );
+}
+
+#[test]
+fn try_from_params_rejects_invalid_page_number_and_size() {
+    let _ = env_logger::try_init();
+
+    let errors = Query::try_from_params("page[number]=abc&page[size]=xyz").unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            QueryError::InvalidPageNumber { raw: "abc".into() },
+            QueryError::InvalidPageSize { raw: "xyz".into() },
+        ]
+    );
+}
+
+#[test]
+fn try_from_params_rejects_invalid_cursor_page_size() {
+    let _ = env_logger::try_init();
+
+    let errors = Query::try_from_params("page[cursor]=abc123&page[size]=xyz").unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![QueryError::InvalidPageSize { raw: "xyz".into() }]
+    );
+}
+
+#[test]
+fn pagination_as_offset_limit_converts_page_number_form() {
+    let _ = env_logger::try_init();
+
+    assert_eq!(
+        Pagination::Paged { number: 1, size: 25 }.as_offset_limit(),
+        (0, 25)
+    );
+    assert_eq!(
+        Pagination::Paged { number: 3, size: 25 }.as_offset_limit(),
+        (50, 25)
+    );
+    assert_eq!(
+        Pagination::Offset { offset: 5, limit: 10 }.as_offset_limit(),
+        (5, 10)
+    );
+}
+
+#[test]
+fn pagination_as_offset_limit_saturates_instead_of_overflowing() {
+    let _ = env_logger::try_init();
+
+    let (offset, limit) = Pagination::Paged {
+        number: i64::MAX,
+        size: 2,
+    }
+    .as_offset_limit();
+
+    assert_eq!(offset, i64::MAX);
+    assert_eq!(limit, 2);
+}
+
+#[test]
+fn pagination_round_trips_each_form() {
+    let _ = env_logger::try_init();
+
+    assert_eq!(
+        Pagination::Offset { offset: 10, limit: 5 }.to_params(),
+        "page[limit]=5&page[offset]=10"
+    );
+    assert_eq!(
+        Pagination::Paged { number: 2, size: 10 }.to_params(),
+        "page[number]=2&page[size]=10"
+    );
+    assert_eq!(
+        Pagination::Cursor {
+            cursor: "abc123".into(),
+            size: 10
+        }
+        .to_params(),
+        "page[cursor]=abc123&page[size]=10"
+    );
+}
+
+#[test]
+fn sort_field_parses_descending_prefix() {
+    let _ = env_logger::try_init();
+
+    assert_eq!(
+        SortField::parse("-created"),
+        SortField {
+            field: "created".into(),
+            descending: true,
+        }
+    );
+    assert_eq!(
+        SortField::parse("name"),
+        SortField {
+            field: "name".into(),
+            descending: false,
+        }
+    );
+}
+
+#[test]
+fn query_apply_sort_is_stable_multi_key() {
+    let _ = env_logger::try_init();
+
+    let mut alice_old = HashMap::new();
+    alice_old.insert("name".to_string(), json!("alice"));
+    alice_old.insert("age".to_string(), json!(40));
+
+    let mut alice_young = HashMap::new();
+    alice_young.insert("name".to_string(), json!("alice"));
+    alice_young.insert("age".to_string(), json!(20));
+
+    let mut bob = HashMap::new();
+    bob.insert("name".to_string(), json!("bob"));
+    bob.insert("age".to_string(), json!(30));
+
+    let query = Query {
+        _type: "people".into(),
+        include: None,
+        fields: None,
+        page: None,
+        sort: Some(vec![
+            SortField {
+                field: "name".into(),
+                descending: false,
+            },
+            SortField {
+                field: "age".into(),
+                descending: true,
+            },
+        ]),
+        filter: None,
+    };
+
+    let mut docs = vec![
+        resource(alice_old.clone()),
+        resource(bob.clone()),
+        resource(alice_young.clone()),
+    ];
+    query.apply_sort(&mut docs);
+
+    assert_eq!(docs[0].attributes.get("age"), Some(&json!(40)));
+    assert_eq!(docs[1].attributes.get("age"), Some(&json!(20)));
+    assert_eq!(docs[2].attributes.get("name"), Some(&json!("bob")));
+}
+
+#[test]
+fn query_apply_sort_leaves_order_unchanged_for_missing_field() {
+    let _ = env_logger::try_init();
+
+    let mut has_field = HashMap::new();
+    has_field.insert("rank".to_string(), json!(1));
+    let missing_field = HashMap::new();
+
+    let query = Query {
+        _type: "people".into(),
+        include: None,
+        fields: None,
+        page: None,
+        sort: Some(vec![SortField {
+            field: "rank".into(),
+            descending: false,
+        }]),
+        filter: None,
+    };
+
+    let mut docs = vec![resource(missing_field.clone()), resource(has_field.clone())];
+    query.apply_sort(&mut docs);
+
+    assert_eq!(docs[0].attributes.get("rank"), None);
+    assert_eq!(docs[1].attributes.get("rank"), Some(&json!(1)));
+}
+
+#[test]
+fn query_apply_sort_puts_explicit_null_last_when_descending() {
+    let _ = env_logger::try_init();
+
+    let mut has_value = HashMap::new();
+    has_value.insert("created".to_string(), json!(5));
+    let mut explicit_null = HashMap::new();
+    explicit_null.insert("created".to_string(), serde_json::Value::Null);
+
+    let query = Query {
+        _type: "people".into(),
+        include: None,
+        fields: None,
+        page: None,
+        sort: Some(vec![SortField {
+            field: "created".into(),
+            descending: true,
+        }]),
+        filter: None,
+    };
+
+    let mut docs = vec![resource(explicit_null.clone()), resource(has_value.clone())];
+    query.apply_sort(&mut docs);
+
+    assert_eq!(docs[0].attributes.get("created"), Some(&json!(5)));
+    assert_eq!(docs[1].attributes.get("created"), Some(&serde_json::Value::Null));
+}
+
+#[test]
+fn include_tree_merges_dotted_paths() {
+    let _ = env_logger::try_init();
+
+    let query = Query {
+        _type: "articles".into(),
+        include: Some(vec!["author".into(), "author.comments.likes".into()]),
+        fields: None,
+        page: None,
+        sort: None,
+        filter: None,
+    };
+
+    let tree = query.include_tree();
+
+    assert!(tree.children.contains_key("author"));
+    let author = &tree.children["author"];
+    assert!(author.children.contains_key("comments"));
+    let comments = &author.children["comments"];
+    assert!(comments.children.contains_key("likes"));
+    assert!(comments.children["likes"].children.is_empty());
+}
+
+#[test]
+fn include_tree_is_empty_without_include() {
+    let _ = env_logger::try_init();
+
+    let query = Query {
+        _type: "articles".into(),
+        include: None,
+        fields: None,
+        page: None,
+        sort: None,
+        filter: None,
+    };
+
+    assert_eq!(query.include_tree(), IncludeTree::default());
+}
+
+#[test]
+fn apply_fields_drops_unlisted_attributes() {
+    let _ = env_logger::try_init();
+
+    let mut fields = BTreeMap::new();
+    fields.insert("people".to_string(), vec!["name".to_string()]);
+
+    let query = Query {
+        _type: "none".into(),
+        include: None,
+        fields: Some(fields),
+        page: None,
+        sort: None,
+        filter: None,
+    };
+
+    let mut attrs = HashMap::new();
+    attrs.insert("name".to_string(), json!("bob"));
+    attrs.insert("email".to_string(), json!("bob@example.com"));
+    let mut doc = resource(attrs);
+
+    query.apply_fields(&mut doc);
+
+    assert_eq!(doc.attributes.get("name"), Some(&json!("bob")));
+    assert_eq!(doc.attributes.get("email"), None);
+}
+
+#[test]
+fn apply_fields_leaves_resource_untouched_without_matching_type() {
+    let _ = env_logger::try_init();
+
+    let mut fields = BTreeMap::new();
+    fields.insert("articles".to_string(), vec!["title".to_string()]);
+
+    let query = Query {
+        _type: "none".into(),
+        include: None,
+        fields: Some(fields),
+        page: None,
+        sort: None,
+        filter: None,
+    };
+
+    let mut attrs = HashMap::new();
+    attrs.insert("name".to_string(), json!("bob"));
+    let mut doc = resource(attrs);
+
+    query.apply_fields(&mut doc);
+
+    assert_eq!(doc.attributes.get("name"), Some(&json!("bob")));
+}